@@ -16,7 +16,7 @@ import psutil
 from fastapi import FastAPI, HTTPException, Request
 from fastapi.middleware.cors import CORSMiddleware
 from fastapi.responses import StreamingResponse
-from pydantic import BaseModel, Field
+from pydantic import BaseModel, Field, model_validator
 from pydantic_settings import BaseSettings, SettingsConfigDict
 
 
@@ -36,8 +36,12 @@ MODEL_REGISTRY: dict[str, dict] = {
     "ai4all/codellama:34b": {"ollama": "codellama:34b", "category": "code",    "description": "Code generation – CodeLlama 34B"},
     "ai4all/mistral":       {"ollama": "mistral",       "category": "general", "description": "Mistral 7B – fast and capable"},
     "ai4all/moondream":     {"ollama": "moondream",     "category": "vision",  "description": "Vision – image analysis"},
+    "ai4all/llava":         {"ollama": "llava",         "category": "vision",  "description": "Vision – LLaVA 7B image understanding"},
+    "ai4all/llama3.2-vision": {"ollama": "llama3.2-vision", "category": "vision", "description": "Vision – LLaMA 3.2 11B Vision"},
     "ai4all/phi3":          {"ollama": "phi3",          "category": "general", "description": "Microsoft Phi-3 – efficient reasoning"},
     "ai4all/gemma2":        {"ollama": "gemma2",        "category": "general", "description": "Google Gemma 2 9B"},
+    "ai4all/nomic-embed-text": {"ollama": "nomic-embed-text", "category": "embedding", "description": "Embeddings – Nomic Embed 768d"},
+    "ai4all/bge-m3":        {"ollama": "bge-m3",        "category": "embedding", "description": "Embeddings – BGE-M3 multilingual 1024d"},
 }
 
 _granted_sessions: set[str] = set()
@@ -56,9 +60,34 @@ app.add_middleware(CORSMiddleware, allow_origins=["*"], allow_credentials=True,
                    allow_methods=["*"], allow_headers=["*"])
 
 # ── Schemas ────────────────────────────────────────────────────────────────
+class ImageUrl(BaseModel):
+    url: str
+
+class ContentPart(BaseModel):
+    type: Literal["text", "image_url"]
+    text: Optional[str] = None
+    image_url: Optional[ImageUrl] = None
+
+    @model_validator(mode="after")
+    def _check_payload(self) -> "ContentPart":
+        if self.type == "text" and (self.text is None or self.image_url is not None):
+            raise ValueError("text content part requires text and no image_url")
+        if self.type == "image_url" and (self.image_url is None or self.text is not None):
+            raise ValueError("image_url content part requires image_url and no text")
+        return self
+
 class Message(BaseModel):
     role: Literal["system", "user", "assistant"]
-    content: str
+    content: str | list[ContentPart]
+
+class JsonSchema(BaseModel):
+    name: str = "response"
+    schema_: dict = Field(alias="schema")
+    strict: Optional[bool] = None
+
+class ResponseFormat(BaseModel):
+    type: Literal["text", "json_object", "json_schema"]
+    json_schema: Optional[JsonSchema] = None
 
 class ChatRequest(BaseModel):
     model: str = "ai4all/llama3"
@@ -66,6 +95,19 @@ class ChatRequest(BaseModel):
     stream: bool = False
     temperature: float = Field(0.7, ge=0.0, le=2.0)
     max_tokens: int = Field(2048, gt=0, le=32768)
+    seed: Optional[int] = None
+    top_p: Optional[float] = Field(None, ge=0.0, le=1.0)
+    top_k: Optional[int] = Field(None, gt=0)
+    repeat_penalty: Optional[float] = Field(None, ge=0.0)
+    stop: str | list[str] | None = None
+    response_format: Optional[ResponseFormat] = None
+
+class EmbeddingRequest(BaseModel):
+    model: str = "ai4all/nomic-embed-text"
+    input: str | list[str]
+
+class PullRequest(BaseModel):
+    model: str
 
 class ModelInfo(BaseModel):
     id: str; object: str = "model"; owned_by: str = "ai4all-community"
@@ -91,12 +133,41 @@ def resolve_model(m: str) -> str:
 def count_tokens(text: str) -> int:
     return max(1, len(text.split()))
 
+def message_text(m: Message) -> str:
+    if isinstance(m.content, str): return m.content
+    return "\n".join(p.text for p in m.content if p.type == "text" and p.text)
+
+def message_images(m: Message) -> list[str]:
+    if isinstance(m.content, str): return []
+    images: list[str] = []
+    for p in m.content:
+        if p.type != "image_url": continue
+        if not p.image_url or not p.image_url.url:
+            raise HTTPException(status_code=400, detail="image_url content part requires a url")
+        url = p.image_url.url
+        if not url.startswith("data:") or ";base64," not in url:
+            raise HTTPException(status_code=400, detail="Only base64 data: URLs are supported for images")
+        images.append(url.split(";base64,", 1)[1])
+    return images
+
+def response_format_to_ollama(rf: Optional[ResponseFormat]) -> str | dict | None:
+    if rf is None or rf.type == "text": return None
+    if rf.type == "json_object": return "json"
+    if rf.json_schema is None:
+        raise HTTPException(status_code=400, detail="response_format json_schema requires a schema")
+    return rf.json_schema.schema_
+
 def messages_to_ollama(msgs: list[Message]) -> tuple[str, list[dict]]:
     system = ""
     chat: list[dict] = []
     for m in msgs:
-        if m.role == "system": system = m.content
-        else: chat.append({"role": m.role, "content": m.content})
+        if m.role == "system":
+            if message_images(m):
+                raise HTTPException(status_code=400, detail="System messages cannot contain images")
+            system = message_text(m); continue
+        entry = {"role": m.role, "content": message_text(m)}
+        if images := message_images(m): entry["images"] = images
+        chat.append(entry)
     return system, chat
 
 async def track_tokens(amount: int, model: str) -> None:
@@ -185,6 +256,56 @@ async def list_models():
         for k, v in MODEL_REGISTRY.items()
     ]}
 
+@app.get("/v1/models/local")
+async def list_local_models(request: Request):
+    try:
+        r = await request.app.state.http.get(f"{settings.ollama_url}/api/tags")
+        r.raise_for_status()
+    except httpx.HTTPStatusError as e:
+        raise HTTPException(status_code=502, detail=f"Ollama error: {e.response.text}")
+    except httpx.ConnectError:
+        raise HTTPException(status_code=503, detail="Cannot reach Ollama. Run: ollama serve")
+    return {"object": "list", "data": [
+        {"id": m.get("name"), "size": m.get("size"), "modified_at": m.get("modified_at"),
+         "details": m.get("details", {})}
+        for m in r.json().get("models", [])
+    ]}
+
+@app.post("/v1/models/pull")
+async def pull_model(body: PullRequest, request: Request):
+    payload = {"model": resolve_model(body.model), "stream": True}
+    return StreamingResponse(_stream_pull(request, payload), media_type="text/event-stream")
+
+async def _stream_pull(request: Request, payload: dict):
+    try:
+        async with request.app.state.http.stream("POST", f"{settings.ollama_url}/api/pull", json=payload) as r:
+            if r.status_code >= 400:
+                detail = (await r.aread()).decode(errors="replace")
+                err = {"error": {"message": f"Ollama error: {detail}", "type": "ollama_error"}}
+                yield f"data: {json.dumps(err)}\n\n"
+            else:
+                async for line in r.aiter_lines():
+                    if not line.strip(): continue
+                    try: chunk = json.loads(line)
+                    except json.JSONDecodeError: continue
+                    yield f"data: {json.dumps(chunk)}\n\n"
+                    if chunk.get("status") == "success" or "error" in chunk: break
+    except httpx.ConnectError:
+        yield f'data: {{"error": {{"message": "Cannot reach Ollama", "type": "connection_error"}}}}\n\n'
+    yield "data: [DONE]\n\n"
+
+@app.delete("/v1/models/{name:path}")
+async def delete_model(name: str, request: Request):
+    try:
+        r = await request.app.state.http.request("DELETE", f"{settings.ollama_url}/api/delete",
+                                                 json={"model": resolve_model(name)})
+        r.raise_for_status()
+    except httpx.HTTPStatusError as e:
+        raise HTTPException(status_code=502, detail=f"Ollama error: {e.response.text}")
+    except httpx.ConnectError:
+        raise HTTPException(status_code=503, detail="Cannot reach Ollama. Run: ollama serve")
+    return {"id": name, "object": "model", "deleted": True}
+
 @app.get("/v1/node/status")
 async def node_status(request: Request):
     try:
@@ -226,15 +347,27 @@ async def grant_starter_tokens(body: StarterGrantRequest):
 async def chat_completions(body: ChatRequest, request: Request):
     ollama_model = resolve_model(body.model)
     system, msgs = messages_to_ollama(body.messages)
+    category = MODEL_REGISTRY.get(body.model, {}).get("category")
+    if category == "embedding":
+        raise HTTPException(status_code=400, detail=f"Model {body.model} is an embedding model; use /v1/embeddings")
+    if category and category != "vision" and any("images" in m for m in msgs):
+        raise HTTPException(status_code=400, detail=f"Model {body.model} does not accept image input")
     req_id = f"chatcmpl-{uuid.uuid4().hex[:12]}"
-    prompt_tokens = sum(count_tokens(m.content) for m in body.messages)
+    prompt_tokens = sum(count_tokens(message_text(m)) for m in body.messages)
+
+    options: dict = {"temperature": body.temperature, "num_predict": body.max_tokens}
+    for key in ("seed", "top_p", "top_k", "repeat_penalty"):
+        if (value := getattr(body, key)) is not None: options[key] = value
+    if body.stop is not None:
+        options["stop"] = [body.stop] if isinstance(body.stop, str) else body.stop
 
     payload: dict = {
-        "model": ollama_model, "messages": msgs, "stream": body.stream,
-        "options": {"temperature": body.temperature, "num_predict": body.max_tokens},
+        "model": ollama_model, "messages": msgs, "stream": body.stream, "options": options,
     }
     if system:
         payload["system"] = system
+    if (fmt := response_format_to_ollama(body.response_format)) is not None:
+        payload["format"] = fmt
 
     endpoint = f"{settings.ollama_url}/api/chat"
 
@@ -283,3 +416,31 @@ async def _stream_ollama(request: Request, endpoint: str, payload: dict,
         yield f'data: {{"error": {{"message": "Cannot reach Ollama", "type": "connection_error"}}}}\n\n'
     yield "data: [DONE]\n\n"
     asyncio.create_task(track_tokens(max(1, (prompt_tokens + comp_tokens) // 100), model))
+
+@app.post("/v1/embeddings")
+async def embeddings(body: EmbeddingRequest, request: Request):
+    category = MODEL_REGISTRY.get(body.model, {}).get("category")
+    if category and category != "embedding":
+        raise HTTPException(status_code=400, detail=f"Model {body.model} is not an embedding model")
+    inputs = [body.input] if isinstance(body.input, str) else body.input
+    if not inputs:
+        raise HTTPException(status_code=400, detail="input must not be empty")
+    try:
+        r = await request.app.state.http.post(f"{settings.ollama_url}/api/embed",
+                                              json={"model": resolve_model(body.model), "input": inputs})
+        r.raise_for_status()
+    except httpx.HTTPStatusError as e:
+        raise HTTPException(status_code=502, detail=f"Ollama error: {e.response.text}")
+    except httpx.ConnectError:
+        raise HTTPException(status_code=503, detail="Cannot reach Ollama. Run: ollama serve")
+
+    data = r.json()
+    prompt_tokens = data.get("prompt_eval_count") or sum(count_tokens(t) for t in inputs)
+    asyncio.create_task(track_tokens(max(1, prompt_tokens // 100), body.model))
+
+    return {
+        "object": "list", "model": body.model,
+        "data": [{"object": "embedding", "index": i, "embedding": e}
+                 for i, e in enumerate(data.get("embeddings", []))],
+        "usage": {"prompt_tokens": prompt_tokens, "total_tokens": prompt_tokens},
+    }